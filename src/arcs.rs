@@ -0,0 +1,68 @@
+//! Great-circle arc tessellation for peer-link geometry.
+
+/// Number of intermediate segments sampled along each peer-link arc.
+const ARC_SEGMENTS: usize = 32;
+
+/// A unit-sphere direction matching the globe vertex shader's convention:
+/// `x = cos(lat)*sin(lon)`, `y = sin(lat)`, `z = cos(lat)*cos(lon)`.
+fn to_cartesian(lat: f64, lon: f64) -> (f64, f64, f64) {
+    (lat.cos() * lon.sin(), lat.sin(), lat.cos() * lon.cos())
+}
+
+/// Inverse of `to_cartesian` for a unit-length `(x, y, z)`.
+fn from_cartesian(x: f64, y: f64, z: f64) -> (f64, f64) {
+    (y.clamp(-1.0, 1.0).asin(), x.atan2(z))
+}
+
+/// Samples `segments + 1` points (`segments` pieces) along the great-circle arc from `(lat1, lon1)`
+/// to `(lat2, lon2)` (degrees) via spherical linear interpolation:
+/// `p(t) = (sin((1-t)d)/sin d)*a + (sin(t*d)/sin d)*b`, where `d` is the
+/// angular distance between the endpoints. Returns the points as
+/// independent `LINES`-mode segment pairs (not a `LINE_STRIP`), so multiple
+/// arcs can be concatenated into one buffer without joining unrelated links.
+fn tessellate_arc(lat1: f64, lon1: f64, lat2: f64, lon2: f64, segments: usize) -> Vec<[f32; 2]> {
+    let a = to_cartesian(lat1.to_radians(), lon1.to_radians());
+    let b = to_cartesian(lat2.to_radians(), lon2.to_radians());
+
+    let dot = (a.0 * b.0 + a.1 * b.1 + a.2 * b.2).clamp(-1.0, 1.0);
+    let d = dot.acos();
+    let sin_d = d.sin();
+
+    let points: Vec<[f32; 2]> = (0..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64;
+
+            // Near-antipodal (or coincident) endpoints make slerp's weights
+            // blow up as sin_d -> 0, so fall back to a linear blend there.
+            let (px, py, pz) = if sin_d.abs() < 1e-6 {
+                (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t, a.2 + (b.2 - a.2) * t)
+            } else {
+                let wa = ((1.0 - t) * d).sin() / sin_d;
+                let wb = (t * d).sin() / sin_d;
+                (wa * a.0 + wb * b.0, wa * a.1 + wb * b.1, wa * a.2 + wb * b.2)
+            };
+
+            // Exactly antipodal endpoints make the linear fallback above zero
+            // out at t=0.5 too (b == -a), so px/py/pz has no direction to
+            // normalize -- hold at the first endpoint instead of dividing.
+            let len = (px * px + py * py + pz * pz).sqrt();
+            let (lat, lon) =
+                if len < 1e-9 { (lat1.to_radians(), lon1.to_radians()) } else { from_cartesian(px / len, py / len, pz / len) };
+
+            [lon.to_degrees() as f32, lat.to_degrees() as f32]
+        })
+        .collect();
+
+    points.windows(2).flat_map(|w| [w[0], w[1]]).collect()
+}
+
+/// Tessellates every `(lat, lon)` peer-link pair into great-circle arcs and
+/// flattens them into one `LINES`-mode vertex buffer ready for
+/// `GraphicsContext::set_links`, which uploads it to the links buffer.
+pub fn tessellate_links(links: &[((f64, f64), (f64, f64))]) -> Vec<f32> {
+    links
+        .iter()
+        .flat_map(|&((lat1, lon1), (lat2, lon2))| tessellate_arc(lat1, lon1, lat2, lon2, ARC_SEGMENTS))
+        .flatten()
+        .collect()
+}
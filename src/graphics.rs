@@ -0,0 +1,212 @@
+use glow::HasContext;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+
+use crate::arcs;
+use crate::shader_program::ShaderProgram;
+use crate::GlError;
+
+const VERT_SHADER_SRC: &str = r##"#version 300 es
+in vec2 pos;
+const float PI = 3.1415926535897932384626433832795;
+uniform vec2 angle;
+
+float degToRad(float v) {
+    return v * PI / 180.0;
+}
+
+mat4 rotateY(float r) {
+    float s = sin(r), c = cos(r);
+    // left handed rotation
+    return mat4(c,   0.0, -s,  0.0,
+                0.0, 1.0, 0.0, 0.0,
+                s,   0.0, c,   0.0,
+                0.0, 0.0, 0.0, 1.0);
+}
+
+mat4 rotateX(float r) {
+    float s = sin(r), c = cos(r);
+    // left handed rotation
+    return mat4(1.0, 0.0, 0.0, 0.0,
+                0.0, c,   s,   0.0,
+                0.0, -s,  c,   0.0,
+                0.0, 0.0, 0.0, 1.0);
+}
+
+void main() {
+    float lat = degToRad(pos.y);
+    float lon = degToRad(pos.x);
+
+    float x = cos(lat) * sin(lon);
+    float y = sin(lat);
+    float z = cos(lat) * cos(lon);
+
+    gl_Position = rotateX(angle[0]) * rotateY(angle[1]) * vec4(x, y, z, 1.0);
+}
+"##;
+
+const FRAG_SHADER_SRC: &str = r##"#version 300 es
+precision highp float;
+out vec4 outColor;
+
+void main() {
+    if (gl_FragCoord.z > 0.5)
+      outColor = vec4(0.0, 0.8, 0.0, 1.0); // green
+    else
+      outColor = vec4(0.8, 0.8, 0.8, 1.0);
+}
+"##;
+
+// Owns the `glow` context, active shader program, VAO and vertex buffer
+// needed to draw a frame.
+pub struct GraphicsContext {
+    gl: glow::Context,
+    program: ShaderProgram,
+    vao: glow::NativeVertexArray,
+    buffer: glow::NativeBuffer,
+    vert_count: i32,
+    links_vao: glow::NativeVertexArray,
+    links_buffer: glow::NativeBuffer,
+    links_vert_count: i32,
+}
+
+impl GraphicsContext {
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_canvas(canvas: &web_sys::HtmlCanvasElement) -> Result<GraphicsContext, GlError> {
+        let webgl2 = canvas
+            .get_context("webgl2")
+            .ok()
+            .flatten()
+            .ok_or_else(|| GlError::Resource(String::from("canvas did not return a webgl2 context")))?
+            .dyn_into::<web_sys::WebGl2RenderingContext>()
+            .map_err(|_| GlError::Resource(String::from("canvas context is not a WebGl2RenderingContext")))?;
+
+        GraphicsContext::new(glow::Context::from_webgl2_context(webgl2))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_offscreen_canvas(canvas: &web_sys::OffscreenCanvas) -> Result<GraphicsContext, GlError> {
+        let webgl2 = canvas
+            .get_context("webgl2")
+            .ok()
+            .flatten()
+            .ok_or_else(|| GlError::Resource(String::from("canvas did not return a webgl2 context")))?
+            .dyn_into::<web_sys::WebGl2RenderingContext>()
+            .map_err(|_| GlError::Resource(String::from("canvas context is not a WebGl2RenderingContext")))?;
+
+        GraphicsContext::new(glow::Context::from_webgl2_context(webgl2))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_loader_function<F>(loader_function: F) -> Result<GraphicsContext, GlError>
+    where
+        F: FnMut(&str) -> *const (),
+    {
+        GraphicsContext::new(unsafe { glow::Context::from_loader_function(loader_function) })
+    }
+
+    fn new(gl: glow::Context) -> Result<GraphicsContext, GlError> {
+        let mut program = ShaderProgram::new(&gl, VERT_SHADER_SRC, FRAG_SHADER_SRC)?;
+        program.use_program(&gl);
+        program.set_uniform_vec2(&gl, "angle", [0.0, 0.0]);
+
+        let position_attribute_location = program.attrib_location(&gl, "pos").unwrap_or(0);
+        let (vao, buffer) = GraphicsContext::create_mesh(&gl, position_attribute_location)?;
+        let (links_vao, links_buffer) = GraphicsContext::create_mesh(&gl, position_attribute_location)?;
+
+        unsafe { gl.enable(glow::DEPTH_TEST) };
+
+        // Now that a VAO is bound and its attributes are enabled, validate
+        // against that state rather than the link-time state.
+        program.validate(&gl)?;
+
+        let mut graphics = GraphicsContext {
+            gl,
+            program,
+            vao,
+            buffer,
+            vert_count: 0,
+            links_vao,
+            links_buffer,
+            links_vert_count: 0,
+        };
+
+        let vertices = unsafe {
+            let bytes = include_bytes!("../lines.bytes");
+            std::slice::from_raw_parts(bytes.as_ptr() as *const f32, bytes.len() >> 2).to_owned()
+        };
+        graphics.upload_vertices(&vertices);
+
+        Ok(graphics)
+    }
+
+    // Creates a VAO/buffer pair wired up to the `pos` attribute.
+    fn create_mesh(
+        gl: &glow::Context,
+        position_attribute_location: u32,
+    ) -> Result<(glow::NativeVertexArray, glow::NativeBuffer), GlError> {
+        let buffer = unsafe { gl.create_buffer() }.map_err(GlError::Resource)?;
+        let vao = unsafe { gl.create_vertex_array() }.map_err(GlError::Resource)?;
+
+        unsafe {
+            gl.bind_vertex_array(Some(vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            gl.vertex_attrib_pointer_f32(position_attribute_location, 2, glow::FLOAT, false, 0, 0);
+            gl.enable_vertex_attrib_array(position_attribute_location);
+        }
+
+        Ok((vao, buffer))
+    }
+
+    fn upload(gl: &glow::Context, buffer: glow::NativeBuffer, vertices: &[f32]) {
+        unsafe {
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+
+            // Reinterpreting the `f32` slice as bytes avoids a copy; `glow`
+            // (unlike `web_sys::Float32Array::view`) takes a plain `&[u8]`
+            // that isn't tied to the WebAssembly memory buffer's lifetime.
+            let bytes = std::slice::from_raw_parts(vertices.as_ptr() as *const u8, std::mem::size_of_val(vertices));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytes, glow::STATIC_DRAW);
+        }
+    }
+
+    pub fn upload_vertices(&mut self, vertices: &[f32]) {
+        GraphicsContext::upload(&self.gl, self.buffer, vertices);
+        self.vert_count = vertices.len() as i32 >> 1;
+    }
+
+    pub fn vertex_count(&self) -> i32 {
+        self.vert_count
+    }
+
+    pub fn set_links(&mut self, links: &[((f64, f64), (f64, f64))]) {
+        let vertices = arcs::tessellate_links(links);
+        GraphicsContext::upload(&self.gl, self.links_buffer, &vertices);
+        self.links_vert_count = vertices.len() as i32 >> 1;
+    }
+
+    pub fn set_angle(&mut self, lat: f32, lon: f32) {
+        self.program.set_uniform_vec2(&self.gl, "angle", [lat, lon]);
+    }
+
+    pub fn clear(&self) {
+        unsafe {
+            self.gl.clear_color(0.98, 0.98, 0.98, 1.0);
+            self.gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+    }
+
+    pub fn draw(&self, mode: u32, count: i32) {
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.draw_arrays(mode, 0, count);
+        }
+    }
+
+    pub fn draw_links(&self, mode: u32) {
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.links_vao));
+            self.gl.draw_arrays(mode, 0, self.links_vert_count);
+        }
+    }
+}
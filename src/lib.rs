@@ -1,16 +1,32 @@
+#[cfg(target_arch = "wasm32")]
 use std::cell::Cell;
+#[cfg(target_arch = "wasm32")]
 use std::cell::RefCell;
+#[cfg(target_arch = "wasm32")]
 use std::rc::Rc;
+use glow::HasContext;
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::JsCast;
+#[cfg(target_arch = "wasm32")]
 use web_sys::CanvasRenderingContext2d;
-use web_sys::{WebGlProgram, WebGl2RenderingContext, WebGlShader};
+
+mod arcs;
+mod graphics;
+mod shader_program;
+#[cfg(target_arch = "wasm32")]
+mod worker;
 
 struct Peer {
     lat: f64,
     lon: f64,
     x: f64,
-    y: f64
+    y: f64,
+    /// Depth after the last `rotate`, in the vertex shader's own
+    /// `rotateX`/`rotateY` convention: negative means the peer has rotated
+    /// onto the far hemisphere and faces away from the viewer.
+    z: f64,
 }
 
 impl Peer {
@@ -19,140 +35,63 @@ impl Peer {
         let (lat, lon) = (lat * std::f64::consts::PI / 180.0, lon * std::f64::consts::PI / 180.0);
         // calculate 2d cartesian coordinates
         let (x,y, _) = Peer::cartesian(lat, lon);
+        let z = Peer::rotated_depth(lat, lon, 0.0, 0.0);
 
-        Peer {lat, lon, x, y}
+        Peer {lat, lon, x, y, z}
     }
 
     fn cartesian(lat: f64, lon: f64) -> (f64, f64, f64) {
         (lat.cos() * lon.sin(), lat.sin(), lat.cos() * (lon + std::f64::consts::PI).cos())
     }
 
+    /// Depth of a peer at `(peer_lat, peer_lon)` under the same
+    /// `rotateX(lat) * rotateY(lon)` composition the vertex shader applies,
+    /// using its `z = cos(lat)*cos(lon)` convention (unlike `cartesian`'s,
+    /// which is shifted by PI for the 2D overlay's own `x`/`y` projection).
+    fn rotated_depth(peer_lat: f64, peer_lon: f64, lat: f64, lon: f64) -> f64 {
+        let (x, y, z) = (peer_lat.cos() * peer_lon.sin(), peer_lat.sin(), peer_lat.cos() * peer_lon.cos());
+
+        let z = -lon.sin() * x + lon.cos() * z;
+        lat.sin() * y + lat.cos() * z
+    }
+
     fn rotate(&mut self, lat: f64, lon: f64) {
         let (x, y, z) = Peer::cartesian(self.lat, self.lon);
 
         self.x = lon.cos() * x - lon.sin() * z;
         self.y = lat.cos() * y + lat.sin() * lon.cos() * z + lat.sin() * lon.sin() * x;
+        self.z = Peer::rotated_depth(self.lat, self.lon, lat, lon);
     }
 
+    #[cfg(target_arch = "wasm32")]
     fn draw(&self, context: &CanvasRenderingContext2d) {
         context.begin_path();
         context.arc(self.x * 360.0 + 360.0, -self.y * 360.0 + 360.0, 4.0, 0.0, std::f64::consts::PI * 2.0).unwrap();
-        context.set_fill_style(&JsValue::from_str("red"));
+        // fade peers that have rotated onto the far hemisphere instead of
+        // painting them at full opacity over the front-facing geometry
+        let alpha = if self.z < 0.0 { 0.25 } else { 1.0 };
+        context.set_fill_style(&JsValue::from_str(&format!("rgba(255, 0, 0, {alpha})")));
         context.fill();
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
     let document = web_sys::window().unwrap().document().unwrap();
     let canvas = document.get_element_by_id("canvas").unwrap();
     let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
 
-    let context = canvas
-        .get_context("webgl2")?
-        .unwrap()
-        .dyn_into::<WebGl2RenderingContext>()?;
-
-    let vert_shader = compile_shader(
-        &context,
-        WebGl2RenderingContext::VERTEX_SHADER,
-        r##"#version 300 es
-        in vec2 pos;
-        const float PI = 3.1415926535897932384626433832795;
-        uniform vec2 angle;
-
-        float degToRad(float v) {
-            return v * PI / 180.0;
-        }
-
-        mat4 rotateY(float r) {
-            float s = sin(r), c = cos(r);
-            // left handed rotation
-            return mat4(c,   0.0, -s,  0.0, 
-                        0.0, 1.0, 0.0, 0.0, 
-                        s,   0.0, c,   0.0, 
-                        0.0, 0.0, 0.0, 1.0);
-        }
-
-        mat4 rotateX(float r) {
-            float s = sin(r), c = cos(r);
-            // left handed rotation
-            return mat4(1.0, 0.0, 0.0, 0.0, 
-                        0.0, c,   s,   0.0, 
-                        0.0, -s,  c,   0.0, 
-                        0.0, 0.0, 0.0, 1.0);
-        }
-
-        void main() {
-            float lat = degToRad(pos.y);
-            float lon = degToRad(pos.x);
-
-            float x = cos(lat) * sin(lon);
-            float y = sin(lat);
-            float z = cos(lat) * cos(lon);
+    // hand the globe canvas off to a worker so drawing doesn't block the main thread
+    let offscreen = canvas.transfer_control_to_offscreen()?;
+    let worker = web_sys::Worker::new("./worker.js")?;
 
-            gl_Position = rotateX(angle[0]) * rotateY(angle[1]) * vec4(x, y, z, 1.0);
-        }
-    "##,
-    )?;
-    let frag_shader = compile_shader(
-        &context,
-        WebGl2RenderingContext::FRAGMENT_SHADER,
-        r##"#version 300 es
-        precision highp float;
-        out vec4 outColor;
-
-        void main() {
-            if (gl_FragCoord.z > 0.5)
-              outColor = vec4(0.0, 0.8, 0.0, 1.0); // green
-            else
-              outColor = vec4(0.8, 0.8, 0.8, 1.0);
-        }
-    "##,
-    )?;
-    let program = link_program(&context, &vert_shader, &frag_shader)?;
-    context.use_program(Some(&program));
-
-    let position_attribute_location = context.get_attrib_location(&program, "pos");
-    let buffer = context.create_buffer().ok_or("failed to create buffer")?;
-    context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
-
-    // Note that `Float32Array::view` is somewhat dangerous (hence the
-    // `unsafe`!). This is creating a raw view into our module's
-    // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
-    // (aka do a memory allocation in Rust) it'll cause the buffer to change,
-    // causing the `Float32Array` to be invalid.
-    //
-    // As a result, after `Float32Array::view` we have to be very careful not to
-    // do any memory allocations before it's dropped.
-    let vert_count = unsafe {
-        let bytes = include_bytes!("../lines.bytes");
-        let vertices = std::slice::from_raw_parts(bytes.as_ptr() as *const f32, bytes.len() >> 2);
-        let positions_array_buf_view = js_sys::Float32Array::view(&vertices.to_owned());
-        // let positions_array_buf_view = js_sys::Float32Array::view_mut_raw(bytes.as_ptr() as *mut f32, bytes.len() >> 2);
-
-        context.buffer_data_with_array_buffer_view(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            &positions_array_buf_view,
-            WebGl2RenderingContext::STATIC_DRAW,
-        );
-
-        bytes.len() as i32 >> 3
-    };
+    let init_message = js_sys::Array::of2(&JsValue::from_str("init"), &offscreen);
+    let transfer = js_sys::Array::of1(&offscreen);
+    worker.post_message_with_transfer(&init_message, &transfer)?;
 
-    let vao = context
-        .create_vertex_array()
-        .ok_or("Could not create vertex array object")?;
-    context.bind_vertex_array(Some(&vao));
-
-    context.vertex_attrib_pointer_with_i32(0, 2, WebGl2RenderingContext::FLOAT, false, 0, 0);
-    context.enable_vertex_attrib_array(position_attribute_location as u32);
-
-    context.enable(WebGl2RenderingContext::DEPTH_TEST);
-
-    let location = context.get_uniform_location(&program, "angle");
-    let angle = Rc::new(Cell::new([0.0,0.0]));
-    context.uniform2fv_with_f32_array(location.as_ref(), &angle.get());
+    let angle = Rc::new(Cell::new([0.0f32, 0.0]));
+    post_angle(&worker, angle.get());
 
     let canvas = document.get_element_by_id("peers_canvas").unwrap();
     let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
@@ -165,18 +104,29 @@ pub fn start() -> Result<(), JsValue> {
         Peer::new(-19.002846, 46.460938), // Madagascar
     ];
 
+    // connect every peer to every other one and hand the links off to the
+    // worker so it can render them as great-circle arcs alongside the globe
+    let links: Vec<((f64, f64), (f64, f64))> = peers
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| {
+            peers[i + 1..]
+                .iter()
+                .map(move |b| ((a.lat.to_degrees(), a.lon.to_degrees()), (b.lat.to_degrees(), b.lon.to_degrees())))
+        })
+        .collect();
+    post_links(&worker, &links);
+
     let peers = Rc::new(RefCell::new(peers));
 
-    // closure for drawing globe and peers
+    // closure for drawing the globe frame and peers; the globe mesh itself
+    // is drawn off-thread by the worker in response to `post_angle`
     let draw = {
         let peers = peers.clone();
+        let worker = worker.clone();
         let canvas_context = canvas.get_context("2d")?.unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>()?;
         move |lat: f32, lon: f32| {
-            // draw globe using webgl context
-            context.uniform2fv_with_f32_array(location.as_ref(), &[lat, lon]);
-            context.clear_color(0.98, 0.98, 0.98, 1.0);
-            context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
-            context.draw_arrays(WebGl2RenderingContext::LINE_STRIP, 0, vert_count);
+            post_angle(&worker, [lat, lon]);
 
             // draw globe frame
             canvas_context.clear_rect(0.0, 0.0, 720.0, 720.0);
@@ -184,7 +134,7 @@ pub fn start() -> Result<(), JsValue> {
             canvas_context.arc(360.0, 360.0, 360.0, 0.0, std::f64::consts::PI * 2.0).unwrap();
             canvas_context.stroke();
             canvas_context.close_path();
-            
+
             // draw peers on map
             for p in peers.borrow_mut().iter_mut() {
                 p.rotate(lat.into(), lon.into());
@@ -246,7 +196,8 @@ pub fn start() -> Result<(), JsValue> {
         let closure: Closure<dyn FnMut(_)> = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
             peers.borrow().iter().for_each(|peer| {
                 let (px, py) = (360.0 + peer.x *  360.0, 360.0 - peer.y * 360.0);
-                if (e.client_x() as f64 - px).abs() < 10.0 && (e.client_y() as f64 - py).abs() < 10.0 {
+                // ignore hits on peers occluded by the far side of the globe
+                if peer.z >= 0.0 && (e.client_x() as f64 - px).abs() < 10.0 && (e.client_y() as f64 - py).abs() < 10.0 {
                     let [mut lat, mut lon] = angle.get();
                     let (dlat, dlon) = (peer.lat as f32 - lat, peer.lon as f32 + lon);
                     let angle = angle.clone();
@@ -290,49 +241,136 @@ pub fn start() -> Result<(), JsValue> {
 
 }
 
+#[cfg(target_arch = "wasm32")]
 fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
 
-pub fn compile_shader(context: &WebGl2RenderingContext, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
-    let shader = context
-        .create_shader(shader_type)
-        .ok_or_else(|| String::from("Unable to create shader object"))?;
-    context.shader_source(&shader, source);
-    context.compile_shader(&shader);
-
-    if context
-        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
+#[cfg(target_arch = "wasm32")]
+fn post_angle(worker: &web_sys::Worker, angle: [f32; 2]) {
+    let message = js_sys::Array::of2(&JsValue::from_f64(angle[0] as f64), &JsValue::from_f64(angle[1] as f64));
+    worker.post_message(&message).expect("failed to post angle to worker");
+}
+
+// Posts the peer-link list as a `"links"`-tagged message, flattened to
+// `[lat1, lon1, lat2, lon2, ...]` degrees.
+#[cfg(target_arch = "wasm32")]
+fn post_links(worker: &web_sys::Worker, links: &[((f64, f64), (f64, f64))]) {
+    let flat: Vec<f64> = links.iter().flat_map(|&((lat1, lon1), (lat2, lon2))| [lat1, lon1, lat2, lon2]).collect();
+    let data = js_sys::Float64Array::from(flat.as_slice());
+    let message = js_sys::Array::of2(&JsValue::from_str("links"), &data);
+    worker.post_message(&message).expect("failed to post peer links to worker");
+}
+
+// Which stage of the program a shader-compile failure came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderKind {
+    Vertex,
+    Fragment,
+}
+
+impl std::fmt::Display for ShaderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderKind::Vertex => write!(f, "vertex"),
+            ShaderKind::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GlError {
+    ShaderCompile { kind: ShaderKind, log: String },
+    ProgramLink(String),
+    ProgramValidate(String),
+    Resource(String),
+}
+
+impl std::fmt::Display for GlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlError::ShaderCompile { kind, log } => write!(f, "{} shader failed to compile: {}", kind, log),
+            GlError::ProgramLink(log) => write!(f, "program failed to link: {}", log),
+            GlError::ProgramValidate(log) => write!(f, "program failed to validate: {}", log),
+            GlError::Resource(log) => write!(f, "failed to create GL resource: {}", log),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<GlError> for JsValue {
+    fn from(err: GlError) -> JsValue {
+        JsValue::from_str(&err.to_string())
+    }
+}
+
+pub fn compile_shader(gl: &glow::Context, shader_type: u32, source: &str) -> Result<glow::NativeShader, GlError> {
+    let kind = if shader_type == glow::VERTEX_SHADER {
+        ShaderKind::Vertex
+    } else {
+        ShaderKind::Fragment
+    };
+
+    let shader = unsafe { gl.create_shader(shader_type) }.map_err(|log| GlError::ShaderCompile { kind, log })?;
+
+    unsafe {
+        gl.shader_source(shader, source);
+        gl.compile_shader(shader);
+    }
+
+    if unsafe { gl.get_shader_compile_status(shader) } {
         Ok(shader)
     } else {
-        Err(context
-            .get_shader_info_log(&shader)
-            .unwrap_or_else(|| String::from("Unknown error creating shader")))
+        Err(GlError::ShaderCompile { kind, log: unsafe { gl.get_shader_info_log(shader) } })
     }
 }
 
-pub fn link_program(context: &WebGl2RenderingContext, vert_shader: &WebGlShader, frag_shader: &WebGlShader) -> Result<WebGlProgram, String> {
-    let program = context
-        .create_program()
-        .ok_or_else(|| String::from("Unable to create shader object"))?;
+#[cfg(test)]
+mod tests {
+    use super::Peer;
+
+    // Reference implementation of the vertex shader's
+    // `rotateX(lat) * rotateY(lon) * vec4(x, y, z, 1.0)` matrix composition,
+    // built independently of `rotated_depth` so a regression there doesn't
+    // also break the test.
+    fn shader_depth(peer_lat: f64, peer_lon: f64, lat: f64, lon: f64) -> f64 {
+        let (x, y, z) = (peer_lat.cos() * peer_lon.sin(), peer_lat.sin(), peer_lat.cos() * peer_lon.cos());
+
+        // rotateY(lon), z row only: row2 = -s*x + c*z
+        let z = -lon.sin() * x + lon.cos() * z;
+        // rotateX(lat), z row only: row2 = s*y + c*z
+        lat.sin() * y + lat.cos() * z
+    }
 
-    context.attach_shader(&program, vert_shader);
-    context.attach_shader(&program, frag_shader);
-    context.link_program(&program);
+    #[test]
+    fn rotated_depth_matches_shader_transform() {
+        let (peer_lat, peer_lon) = (60f64.to_radians(), 100f64.to_radians());
+        let (lat, lon) = (0.8, -0.3);
 
-    if context
-        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
+        let expected = shader_depth(peer_lat, peer_lon, lat, lon);
+        let actual = Peer::rotated_depth(peer_lat, peer_lon, lat, lon);
+
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+}
+
+pub fn link_program(
+    gl: &glow::Context,
+    vert_shader: glow::NativeShader,
+    frag_shader: glow::NativeShader,
+) -> Result<glow::NativeProgram, GlError> {
+    let program = unsafe { gl.create_program() }.map_err(GlError::ProgramLink)?;
+
+    unsafe {
+        gl.attach_shader(program, vert_shader);
+        gl.attach_shader(program, frag_shader);
+        gl.link_program(program);
+    }
+
+    if unsafe { gl.get_program_link_status(program) } {
         Ok(program)
     } else {
-        Err(context
-            .get_program_info_log(&program)
-            .unwrap_or_else(|| String::from("Unknown error creating program object")))
+        Err(GlError::ProgramLink(unsafe { gl.get_program_info_log(program) }))
     }
 }
 
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use glow::HasContext;
+
+use crate::{compile_shader, link_program, GlError};
+
+// Wraps a linked `glow` program and caches its uniform locations.
+pub struct ShaderProgram {
+    program: glow::NativeProgram,
+    uniform_locations: HashMap<String, glow::NativeUniformLocation>,
+}
+
+impl ShaderProgram {
+    pub fn new(gl: &glow::Context, vert_src: &str, frag_src: &str) -> Result<ShaderProgram, GlError> {
+        let vert_shader = compile_shader(gl, glow::VERTEX_SHADER, vert_src)?;
+        let frag_shader = compile_shader(gl, glow::FRAGMENT_SHADER, frag_src)?;
+        let program = link_program(gl, vert_shader, frag_shader)?;
+
+        unsafe {
+            gl.delete_shader(vert_shader);
+            gl.delete_shader(frag_shader);
+        }
+
+        Ok(ShaderProgram { program, uniform_locations: HashMap::new() })
+    }
+
+    pub fn use_program(&self, gl: &glow::Context) {
+        unsafe { gl.use_program(Some(self.program)) };
+    }
+
+    /// `glValidateProgram`'s result depends on the GL state at the time it's
+    /// called (bound VAO, enabled attributes, bound textures), so this should
+    /// be called once that state is set up, right before the first draw --
+    /// not right after linking.
+    pub fn validate(&self, gl: &glow::Context) -> Result<(), GlError> {
+        unsafe { gl.validate_program(self.program) };
+
+        if unsafe { gl.get_program_validate_status(self.program) } {
+            Ok(())
+        } else {
+            Err(GlError::ProgramValidate(unsafe { gl.get_program_info_log(self.program) }))
+        }
+    }
+
+    pub fn attrib_location(&self, gl: &glow::Context, name: &str) -> Option<u32> {
+        unsafe { gl.get_attrib_location(self.program, name) }
+    }
+
+    fn uniform_location(&mut self, gl: &glow::Context, name: &str) -> Option<glow::NativeUniformLocation> {
+        if let Some(location) = self.uniform_locations.get(name) {
+            return Some(location.clone());
+        }
+
+        let location = unsafe { gl.get_uniform_location(self.program, name) }?;
+        self.uniform_locations.insert(name.to_string(), location.clone());
+        Some(location)
+    }
+
+    pub fn set_uniform_vec2(&mut self, gl: &glow::Context, name: &str, value: [f32; 2]) {
+        let location = self.uniform_location(gl, name);
+        unsafe { gl.uniform_2_f32(location.as_ref(), value[0], value[1]) };
+    }
+
+    pub fn set_uniform_mat4(&mut self, gl: &glow::Context, name: &str, value: &[f32; 16]) {
+        let location = self.uniform_location(gl, name);
+        unsafe { gl.uniform_matrix_4_f32_slice(location.as_ref(), false, value) };
+    }
+}
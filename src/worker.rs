@@ -0,0 +1,45 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, OffscreenCanvas};
+
+use crate::graphics::GraphicsContext;
+
+// Entry point invoked by the worker bootstrap script once the wasm module
+// has initialized and the `OffscreenCanvas` transferred from the main
+// thread has arrived.
+#[wasm_bindgen]
+pub fn worker_entry_point(offscreen_canvas: OffscreenCanvas) -> Result<(), JsValue> {
+    let mut graphics = GraphicsContext::from_offscreen_canvas(&offscreen_canvas)?;
+    redraw(&graphics);
+
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let closure: Closure<dyn FnMut(MessageEvent)> = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let message: js_sys::Array = event.data().unchecked_into();
+
+        if message.get(0).as_string().as_deref() == Some("links") {
+            let flat: js_sys::Float64Array = message.get(1).unchecked_into();
+            let links: Vec<((f64, f64), (f64, f64))> =
+                flat.to_vec().chunks_exact(4).map(|c| ((c[0], c[1]), (c[2], c[3]))).collect();
+
+            graphics.set_links(&links);
+        } else {
+            let lat = message.get(0).as_f64().unwrap_or(0.0) as f32;
+            let lon = message.get(1).as_f64().unwrap_or(0.0) as f32;
+
+            graphics.set_angle(lat, lon);
+        }
+
+        redraw(&graphics);
+    }));
+
+    scope.set_onmessage(Some(closure.as_ref().unchecked_ref()));
+    closure.forget();
+
+    Ok(())
+}
+
+fn redraw(graphics: &GraphicsContext) {
+    graphics.clear();
+    graphics.draw(glow::LINE_STRIP, graphics.vertex_count());
+    graphics.draw_links(glow::LINES);
+}